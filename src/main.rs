@@ -1,26 +1,102 @@
-use std::io::{self, Write};
+use std::collections::HashMap;
+use std::env;
+use std::process;
+
+use rustyline::error::ReadlineError;
+use rustyline::DefaultEditor;
 
 #[derive(Debug, Clone)]
 enum Token {
     Number(f64),
+    Const(f64),
+    Func(String),
+    Ident(String),
     Op(char),
     LParen,
     RParen,
 }
 
-fn tokenize(expr: &str) -> Result<Vec<Token>, String> {
+/// The domain-specific failure behind a `CalcError::Math`.
+#[derive(Debug, Clone)]
+enum MathError {
+    DivideByZero,
+    DomainError(String),
+}
+
+/// Central error type for the calculator. `Syntax` carries the byte offset
+/// of the offending character so the REPL can print a caret underneath it.
+#[derive(Debug, Clone)]
+enum CalcError {
+    Syntax { message: String, pos: usize },
+    Parser(String),
+    Math(MathError),
+}
+
+/// Shifts a `Syntax` error's position by `offset`, used when an error from a
+/// sub-expression (e.g. the right-hand side of an assignment) is reported
+/// against the full input line.
+fn shift_syntax_pos(err: CalcError, offset: usize) -> CalcError {
+    match err {
+        CalcError::Syntax { message, pos } => CalcError::Syntax {
+            message,
+            pos: pos + offset,
+        },
+        other => other,
+    }
+}
+
+fn tokenize(expr: &str) -> Result<Vec<Token>, CalcError> {
     let mut tokens = Vec::new();
-    let mut chars = expr.chars().peekable();
+    let mut chars = expr.char_indices().peekable();
 
-    while let Some(&ch) = chars.peek() {
+    while let Some(&(pos, ch)) = chars.peek() {
         if ch.is_whitespace() {
             chars.next();
             continue;
         }
-        if ch.is_digit(10) || ch == '.' {
+        if ch.is_ascii_digit() || ch == '.' {
+            if ch == '0' {
+                let mut lookahead = chars.clone();
+                lookahead.next();
+                let radix = match lookahead.peek() {
+                    Some(&(_, 'x')) | Some(&(_, 'X')) => Some(16),
+                    Some(&(_, 'b')) | Some(&(_, 'B')) => Some(2),
+                    Some(&(_, 'o')) | Some(&(_, 'O')) => Some(8),
+                    _ => None,
+                };
+                if let Some(radix) = radix {
+                    chars.next(); // consume '0'
+                    chars.next(); // consume the radix prefix letter
+                    let mut digits = String::new();
+                    while let Some(&(_, c2)) = chars.peek() {
+                        if c2.is_digit(radix) {
+                            digits.push(c2);
+                            chars.next();
+                        } else {
+                            break;
+                        }
+                    }
+                    if digits.is_empty() {
+                        return Err(CalcError::Syntax {
+                            message: "Invalid integer literal: missing digits".into(),
+                            pos,
+                        });
+                    }
+                    match i64::from_str_radix(&digits, radix) {
+                        Ok(n) => tokens.push(Token::Number(n as f64)),
+                        Err(_) => {
+                            return Err(CalcError::Syntax {
+                                message: format!("Invalid integer literal: {}", digits),
+                                pos,
+                            })
+                        }
+                    }
+                    continue;
+                }
+            }
             let mut num = String::new();
-            while let Some(&c2) = chars.peek() {
-                if c2.is_digit(10) || c2 == '.' {
+            while let Some(&(_, c2)) = chars.peek() {
+                if c2.is_ascii_digit() || c2 == '.' {
                     num.push(c2);
                     chars.next();
                 } else {
@@ -29,15 +105,85 @@ fn tokenize(expr: &str) -> Result<Vec<Token>, String> {
             }
             match num.parse::<f64>() {
                 Ok(n) => tokens.push(Token::Number(n)),
-                Err(_) => return Err(format!("Invalid number: {}", num)),
+                Err(_) => {
+                    return Err(CalcError::Syntax {
+                        message: format!("Invalid number: {}", num),
+                        pos,
+                    })
+                }
+            }
+            continue;
+        }
+        if (ch == 'd' || ch == 'D')
+            && matches!(
+                tokens.last(),
+                Some(Token::Number(_)) | Some(Token::Const(_)) | Some(Token::Ident(_)) | Some(Token::RParen)
+            )
+        {
+            let mut lookahead = chars.clone();
+            lookahead.next();
+            // A following digit is the dice's side count (e.g. "3d6"); only a
+            // following letter/underscore means this is actually the start of
+            // a longer identifier (e.g. "10 density").
+            let starts_longer_ident =
+                matches!(lookahead.peek(), Some(&(_, c)) if c.is_alphabetic() || c == '_');
+            if !starts_longer_ident {
+                tokens.push(Token::Op('d'));
+                chars.next();
+                continue;
+            }
+        }
+        if ch.is_alphabetic() || ch == '_' {
+            let mut name = String::new();
+            while let Some(&(_, c2)) = chars.peek() {
+                if c2.is_alphanumeric() || c2 == '_' {
+                    name.push(c2);
+                    chars.next();
+                } else {
+                    break;
+                }
+            }
+            match name.as_str() {
+                "pi" => tokens.push(Token::Const(std::f64::consts::PI)),
+                "e" => tokens.push(Token::Const(std::f64::consts::E)),
+                "tau" => tokens.push(Token::Const(std::f64::consts::TAU)),
+                "sin" | "cos" | "tan" | "asin" | "acos" | "atan" | "sqrt" | "cbrt" | "ln"
+                | "log" | "exp" | "abs" | "floor" | "ceil" | "round" => {
+                    tokens.push(Token::Func(name))
+                }
+                _ => tokens.push(Token::Ident(name)),
             }
             continue;
         }
         match ch {
-            '+' | '-' | '*' | '/' | '^' | '%' => {
+            '+' | '-' | '*' | '/' | '^' | '%' | '&' | '|' | '~' => {
                 tokens.push(Token::Op(ch));
                 chars.next();
             }
+            '<' => {
+                chars.next();
+                if matches!(chars.peek(), Some(&(_, '<'))) {
+                    chars.next();
+                    tokens.push(Token::Op('L'));
+                } else {
+                    return Err(CalcError::Syntax {
+                        message: "Invalid character: '<'".into(),
+                        pos,
+                    });
+                }
+            }
+            '>' => {
+                chars.next();
+                if matches!(chars.peek(), Some(&(_, '>'))) {
+                    chars.next();
+                    tokens.push(Token::Op('R'));
+                } else {
+                    return Err(CalcError::Syntax {
+                        message: "Invalid character: '>'".into(),
+                        pos,
+                    });
+                }
+            }
             '(' => {
                 tokens.push(Token::LParen);
                 chars.next();
@@ -46,11 +192,18 @@ fn tokenize(expr: &str) -> Result<Vec<Token>, String> {
                 tokens.push(Token::RParen);
                 chars.next();
             }
-            _ => return Err(format!("Invalid character: '{}'", ch)),
+            _ => {
+                return Err(CalcError::Syntax {
+                    message: format!("Invalid character: '{}'", ch),
+                    pos,
+                })
+            }
         }
     }
 
-    // Handle unary minus: convert unary '-' to a '0' then '-'
+    // Handle unary minus: convert unary '-' to a '0' then '-'.
+    // Handle unary '~' (bitwise-not) by relabeling it to 'Op('!')' so
+    // eval_rpn can tell it apart from the binary xor use of '~'.
     let mut fixed = Vec::new();
     let mut i = 0;
     while i < tokens.len() {
@@ -67,6 +220,18 @@ fn tokenize(expr: &str) -> Result<Vec<Token>, String> {
                 continue;
             }
         }
+        if let Token::Op('~') = tokens[i] {
+            let is_unary = if i == 0 {
+                true
+            } else {
+                matches!(tokens[i - 1], Token::Op(_) | Token::LParen)
+            };
+            if is_unary {
+                fixed.push(Token::Op('!'));
+                i += 1;
+                continue;
+            }
+        }
         fixed.push(tokens[i].clone());
         i += 1;
     }
@@ -76,24 +241,78 @@ fn tokenize(expr: &str) -> Result<Vec<Token>, String> {
 
 fn precedence(op: char) -> i32 {
     match op {
-        '+' | '-' => 1,
-        '*' | '/' | '%' => 2,
-        '^' => 3,
+        '&' | '|' | '~' => 1,
+        'L' | 'R' => 2,
+        '+' | '-' => 3,
+        '*' | '/' | '%' => 4,
+        '^' => 5,
+        'd' => 6,
+        '!' => 7,
         _ => 0,
     }
 }
 
 fn is_right_associative(op: char) -> bool {
-    matches!(op, '^')
+    matches!(op, '^' | '!')
+}
+
+fn to_int(x: f64) -> Result<i64, CalcError> {
+    if x.fract().abs() > 1e-9 {
+        return Err(CalcError::Math(MathError::DomainError(format!(
+            "bitwise operators require integer operands, got {}",
+            x
+        ))));
+    }
+    Ok(x as i64)
+}
+
+fn shift_amount(n: i64) -> Result<u32, CalcError> {
+    if !(0..64).contains(&n) {
+        return Err(CalcError::Math(MathError::DomainError(format!(
+            "shift amount must be between 0 and 63, got {}",
+            n
+        ))));
+    }
+    Ok(n as u32)
+}
+
+const MAX_DICE_COUNT: i64 = 10_000;
+const MAX_DICE_SIDES: i64 = 1_000_000;
+
+/// A small xorshift64* PRNG, seedable for reproducible dice rolls.
+struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        Rng {
+            state: (seed ^ 0x9E3779B97F4A7C15) | 1,
+        }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut x = self.state;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.state = x;
+        x.wrapping_mul(0x2545F4914F6CDD1D)
+    }
+
+    fn roll(&mut self, sides: i64) -> i64 {
+        1 + (self.next_u64() % sides as u64) as i64
+    }
 }
 
-fn shunting_yard(tokens: &[Token]) -> Result<Vec<Token>, String> {
+fn shunting_yard(tokens: &[Token]) -> Result<Vec<Token>, CalcError> {
     let mut output: Vec<Token> = Vec::new();
     let mut ops: Vec<Token> = Vec::new();
 
     for token in tokens {
         match token {
-            Token::Number(_) => output.push(token.clone()),
+            Token::Number(_) | Token::Const(_) | Token::Ident(_) => output.push(token.clone()),
+            Token::Func(_) => ops.push(token.clone()),
             Token::Op(op1) => {
                 while let Some(top) = ops.last() {
                     match top {
@@ -105,6 +324,10 @@ fn shunting_yard(tokens: &[Token]) -> Result<Vec<Token>, String> {
                                 continue;
                             }
                         }
+                        Token::Func(_) => {
+                            output.push(ops.pop().unwrap());
+                            continue;
+                        }
                         _ => {}
                     }
                     break;
@@ -120,13 +343,16 @@ fn shunting_yard(tokens: &[Token]) -> Result<Vec<Token>, String> {
                         output.push(t);
                     }
                 }
+                if let Some(Token::Func(_)) = ops.last() {
+                    output.push(ops.pop().unwrap());
+                }
             }
         }
     }
 
     while let Some(t) = ops.pop() {
         if matches!(t, Token::LParen | Token::RParen) {
-            return Err("Mismatched parentheses".into());
+            return Err(CalcError::Parser("Mismatched parentheses".into()));
         }
         output.push(t);
     }
@@ -134,80 +360,352 @@ fn shunting_yard(tokens: &[Token]) -> Result<Vec<Token>, String> {
     Ok(output)
 }
 
-fn eval_rpn(tokens: &[Token]) -> Result<f64, String> {
+fn eval_rpn(
+    tokens: &[Token],
+    radian_mode: bool,
+    vars: &HashMap<String, f64>,
+    rng: &mut Rng,
+) -> Result<f64, CalcError> {
     let mut stack: Vec<f64> = Vec::new();
 
     for token in tokens {
         match token {
             Token::Number(n) => stack.push(*n),
+            Token::Const(n) => stack.push(*n),
+            Token::Ident(name) => {
+                let v = vars
+                    .get(name)
+                    .ok_or_else(|| CalcError::Parser(format!("Unknown variable: {}", name)))?;
+                stack.push(*v);
+            }
+            Token::Func(name) => {
+                let a = stack
+                    .pop()
+                    .ok_or_else(|| CalcError::Parser("Not enough operands for function".into()))?;
+                let res = match name.as_str() {
+                    "sin" => (if radian_mode { a } else { a.to_radians() }).sin(),
+                    "cos" => (if radian_mode { a } else { a.to_radians() }).cos(),
+                    "tan" => (if radian_mode { a } else { a.to_radians() }).tan(),
+                    "asin" => {
+                        let r = a.asin();
+                        if radian_mode { r } else { r.to_degrees() }
+                    }
+                    "acos" => {
+                        let r = a.acos();
+                        if radian_mode { r } else { r.to_degrees() }
+                    }
+                    "atan" => {
+                        let r = a.atan();
+                        if radian_mode { r } else { r.to_degrees() }
+                    }
+                    "sqrt" => {
+                        if a < 0.0 {
+                            return Err(CalcError::Math(MathError::DomainError(
+                                "sqrt of negative number".into(),
+                            )));
+                        }
+                        a.sqrt()
+                    }
+                    "cbrt" => a.cbrt(),
+                    "ln" => {
+                        if a <= 0.0 {
+                            return Err(CalcError::Math(MathError::DomainError(
+                                "ln of non-positive number".into(),
+                            )));
+                        }
+                        a.ln()
+                    }
+                    "log" => {
+                        if a <= 0.0 {
+                            return Err(CalcError::Math(MathError::DomainError(
+                                "log of non-positive number".into(),
+                            )));
+                        }
+                        a.log10()
+                    }
+                    "exp" => a.exp(),
+                    "abs" => a.abs(),
+                    "floor" => a.floor(),
+                    "ceil" => a.ceil(),
+                    "round" => a.round(),
+                    _ => return Err(CalcError::Parser(format!("Unknown function: {}", name))),
+                };
+                stack.push(res);
+            }
             Token::Op(op) => {
                 if *op == '%' {
                     if let Some(a) = stack.pop() {
                         stack.push(a / 100.0);
                     } else {
-                        return Err("Not enough operands for %".into());
+                        return Err(CalcError::Parser("Not enough operands for %".into()));
                     }
                     continue;
                 }
+                if *op == '!' {
+                    let a = stack
+                        .pop()
+                        .ok_or_else(|| CalcError::Parser("Not enough operands for ~".into()))?;
+                    stack.push(!to_int(a)? as f64);
+                    continue;
+                }
 
-                let b = stack.pop().ok_or("Not enough operands")?;
-                let a = stack.pop().ok_or("Not enough operands")?;
+                let b = stack
+                    .pop()
+                    .ok_or_else(|| CalcError::Parser("Not enough operands".into()))?;
+                let a = stack
+                    .pop()
+                    .ok_or_else(|| CalcError::Parser("Not enough operands".into()))?;
                 let res = match op {
                     '+' => a + b,
                     '-' => a - b,
                     '*' => a * b,
                     '/' => {
                         if b == 0.0 {
-                            return Err("Division by zero".into());
+                            return Err(CalcError::Math(MathError::DivideByZero));
                         }
                         a / b
                     }
                     '^' => a.powf(b),
-                    _ => return Err(format!("Unknown operator: {}", op)),
+                    '&' => (to_int(a)? & to_int(b)?) as f64,
+                    '|' => (to_int(a)? | to_int(b)?) as f64,
+                    '~' => (to_int(a)? ^ to_int(b)?) as f64,
+                    'L' => (to_int(a)? << shift_amount(to_int(b)?)?) as f64,
+                    'R' => (to_int(a)? >> shift_amount(to_int(b)?)?) as f64,
+                    'd' => {
+                        let count = to_int(a)?;
+                        let sides = to_int(b)?;
+                        if count <= 0 || sides <= 0 {
+                            return Err(CalcError::Math(MathError::DomainError(
+                                "dice operator requires positive integer operands".into(),
+                            )));
+                        }
+                        if count > MAX_DICE_COUNT || sides > MAX_DICE_SIDES {
+                            return Err(CalcError::Math(MathError::DomainError(format!(
+                                "dice operator limited to {} dice of at most {} sides",
+                                MAX_DICE_COUNT, MAX_DICE_SIDES
+                            ))));
+                        }
+                        (0..count).map(|_| rng.roll(sides)).sum::<i64>() as f64
+                    }
+                    _ => return Err(CalcError::Parser(format!("Unknown operator: {}", op))),
                 };
                 stack.push(res);
             }
-            _ => return Err("Invalid token in RPN".into()),
+            _ => return Err(CalcError::Parser("Invalid token in RPN".into())),
         }
     }
 
     if stack.len() == 1 {
         Ok(stack[0])
     } else {
-        Err("Invalid expression".into())
+        Err(CalcError::Parser("Invalid expression".into()))
     }
 }
 
-fn evaluate(expr: &str) -> Result<f64, String> {
+fn is_reserved_name(name: &str) -> bool {
+    matches!(
+        name,
+        "pi" | "e"
+            | "tau"
+            | "ans"
+            | "sin"
+            | "cos"
+            | "tan"
+            | "asin"
+            | "acos"
+            | "atan"
+            | "sqrt"
+            | "cbrt"
+            | "ln"
+            | "log"
+            | "exp"
+            | "abs"
+            | "floor"
+            | "ceil"
+            | "round"
+    )
+}
+
+fn eval_expr(
+    expr: &str,
+    radian_mode: bool,
+    vars: &HashMap<String, f64>,
+    rng: &mut Rng,
+) -> Result<f64, CalcError> {
     let tokens = tokenize(expr)?;
     let rpn = shunting_yard(&tokens)?;
-    eval_rpn(&rpn)
+    eval_rpn(&rpn, radian_mode, vars, rng)
+}
+
+fn evaluate(
+    expr: &str,
+    radian_mode: bool,
+    vars: &mut HashMap<String, f64>,
+    rng: &mut Rng,
+) -> Result<f64, CalcError> {
+    let result = if let Some(eq_pos) = expr.find('=') {
+        let lhs_raw = &expr[..eq_pos];
+        let name = lhs_raw.trim();
+        let name_start = lhs_raw.len() - lhs_raw.trim_start().len();
+        let rhs = &expr[eq_pos + 1..];
+        if name.is_empty()
+            || !name.chars().next().unwrap().is_alphabetic() && !name.starts_with('_')
+            || !name.chars().all(|c| c.is_alphanumeric() || c == '_')
+        {
+            return Err(CalcError::Syntax {
+                message: format!("Invalid assignment target: '{}'", name),
+                pos: name_start,
+            });
+        }
+        if is_reserved_name(name) {
+            return Err(CalcError::Syntax {
+                message: format!("Cannot assign to reserved name '{}'", name),
+                pos: name_start,
+            });
+        }
+        let value = eval_expr(rhs, radian_mode, vars, rng)
+            .map_err(|e| shift_syntax_pos(e, eq_pos + 1))?;
+        vars.insert(name.to_string(), value);
+        value
+    } else {
+        eval_expr(expr, radian_mode, vars, rng)?
+    };
+    vars.insert("ans".to_string(), result);
+    Ok(result)
+}
+
+fn to_base_string(n: i64, base: u32) -> String {
+    if n == 0 {
+        return "0".to_string();
+    }
+    const DIGITS: &[u8] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+    let neg = n < 0;
+    let mut mag = n.unsigned_abs();
+    let mut buf = Vec::new();
+    while mag > 0 {
+        buf.push(DIGITS[(mag % base as u64) as usize]);
+        mag /= base as u64;
+    }
+    if neg {
+        buf.push(b'-');
+    }
+    buf.reverse();
+    String::from_utf8(buf).unwrap()
+}
+
+fn format_result(value: f64, precision: Option<usize>, base: u32) -> String {
+    if base != 10 {
+        return to_base_string(value.trunc() as i64, base);
+    }
+    match precision {
+        Some(p) => format!("{:.*}", p, value),
+        None => {
+            if value.fract().abs() < 1e-12 {
+                format!("{}", value.trunc() as i64)
+            } else {
+                format!("{}", value)
+            }
+        }
+    }
+}
+
+/// Formats a `CalcError` for display, including a caret line pointing at the
+/// offending byte offset in `source` for `Syntax` errors.
+fn handler(err: &CalcError, source: &str) -> String {
+    match err {
+        CalcError::Syntax { message, pos } => {
+            let caret = " ".repeat(*pos);
+            format!("Syntax error: {}\n  {}\n  {}^", message, source, caret)
+        }
+        CalcError::Parser(message) => format!("Parse error: {}", message),
+        CalcError::Math(MathError::DivideByZero) => "Math error: division by zero".to_string(),
+        CalcError::Math(MathError::DomainError(message)) => format!("Math error: {}", message),
+    }
 }
 
 fn print_help() {
     println!("Rust Calculator REPL");
     println!("Type expressions, e.g.: 2 + 3 * (4 - 1) ^ 2");
     println!("Operators: + - * / ^ % (percent converts number to fraction, e.g. 50% -> 0.5)");
-    println!("Commands: quit, exit, help, clear");
+    println!("Bitwise: & | ~ (infix xor) ~x (bitwise-not) << >> ; literals: 0x1f 0b1010 0o17");
+    println!("Functions: sin cos tan asin acos atan sqrt cbrt ln log exp abs floor ceil round");
+    println!("Constants: pi, e, tau");
+    println!("Variables: x = 3 * 4 assigns, then use x later; ans holds the last result");
+    println!("Dice: NdM rolls N dice of M sides and sums them, e.g. 3d6, 2d20 + 5");
+    println!("Commands: quit, exit, help, clear, rad, deg, fix N, base B (2-36), seed N");
+}
+
+const HISTORY_FILE: &str = "history.txt";
+
+fn default_seed() -> u64 {
+    use std::time::{SystemTime, UNIX_EPOCH};
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or(0x2545F4914F6CDD1D)
+}
+
+fn run_eval(expr: &str) -> i32 {
+    let mut vars: HashMap<String, f64> = HashMap::new();
+    let mut rng = Rng::new(default_seed());
+    match evaluate(expr, true, &mut vars, &mut rng) {
+        Ok(result) => {
+            println!("{}", format_result(result, None, 10));
+            0
+        }
+        Err(e) => {
+            eprintln!("{}", handler(&e, expr));
+            1
+        }
+    }
 }
 
 fn main() {
-    let mut input = String::new();
+    let args: Vec<String> = env::args().collect();
+    if let Some(pos) = args.iter().position(|a| a == "--eval") {
+        let expr = match args.get(pos + 1) {
+            Some(e) => e,
+            None => {
+                eprintln!("Error: --eval requires an expression argument");
+                process::exit(1);
+            }
+        };
+        process::exit(run_eval(expr));
+    }
+
+    let mut radian_mode = true;
+    let mut precision: Option<usize> = None;
+    let mut base: u32 = 10;
+    let mut vars: HashMap<String, f64> = HashMap::new();
+    let mut rng = Rng::new(default_seed());
     print_help();
 
+    let mut rl = DefaultEditor::new().expect("Failed to initialize line editor");
+    let _ = rl.load_history(HISTORY_FILE);
+
     loop {
-        print!("> ");
-        io::stdout().flush().unwrap();
-        input.clear();
-        if io::stdin().read_line(&mut input).is_err() {
-            println!("Input error, try again.");
-            continue;
-        }
+        let line = match rl.readline("> ") {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => {
+                println!("^C");
+                continue;
+            }
+            Err(ReadlineError::Eof) => {
+                println!("Goodbye.");
+                break;
+            }
+            Err(err) => {
+                println!("Input error: {:?}", err);
+                continue;
+            }
+        };
 
-        let line = input.trim();
+        let line = line.trim();
         if line.is_empty() {
             continue;
         }
+        let _ = rl.add_history_entry(line);
+
         match line.to_lowercase().as_str() {
             "quit" | "exit" => {
                 println!("Goodbye.");
@@ -222,19 +720,139 @@ fn main() {
                 print!("\x1B[2J\x1B[1;1H");
                 continue;
             }
+            "rad" => {
+                radian_mode = true;
+                println!("Trig functions now use radians.");
+                continue;
+            }
+            "deg" => {
+                radian_mode = false;
+                println!("Trig functions now use degrees.");
+                continue;
+            }
             _ => {}
         }
 
-        match evaluate(line) {
-            Ok(result) => {
-                // Trim trailing .0 for whole numbers
-                if (result.fract()).abs() < 1e-12 {
-                    println!("{}", result.trunc() as i64);
-                } else {
-                    println!("{}", result);
+        let lower = line.to_lowercase();
+        if let Some(arg) = lower.strip_prefix("fix ") {
+            match arg.trim().parse::<usize>() {
+                Ok(p) => {
+                    precision = Some(p);
+                    println!("Output precision set to {} decimal digits.", p);
+                }
+                Err(_) => println!("Error: Invalid precision '{}'", arg.trim()),
+            }
+            continue;
+        }
+        if let Some(arg) = lower.strip_prefix("base ") {
+            match arg.trim().parse::<u32>() {
+                Ok(b) if (2..=36).contains(&b) => {
+                    base = b;
+                    println!("Output base set to {}.", b);
+                }
+                Ok(_) => println!("Error: Base too large! Accepted ranges: 2–36"),
+                Err(_) => println!("Error: Invalid base '{}'", arg.trim()),
+            }
+            continue;
+        }
+        if let Some(arg) = lower.strip_prefix("seed ") {
+            match arg.trim().parse::<u64>() {
+                Ok(s) => {
+                    rng = Rng::new(s);
+                    println!("PRNG seeded with {}.", s);
                 }
+                Err(_) => println!("Error: Invalid seed '{}'", arg.trim()),
             }
-            Err(e) => println!("Error: {}", e),
+            continue;
+        }
+
+        match evaluate(line, radian_mode, &mut vars, &mut rng) {
+            Ok(result) => println!("{}", format_result(result, precision, base)),
+            Err(e) => println!("{}", handler(&e, line)),
+        }
+    }
+
+    let _ = rl.save_history(HISTORY_FILE);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval(expr: &str) -> Result<f64, CalcError> {
+        let mut vars: HashMap<String, f64> = HashMap::new();
+        let mut rng = Rng::new(1);
+        evaluate(expr, true, &mut vars, &mut rng)
+    }
+
+    #[test]
+    fn bitwise_operators() {
+        assert_eq!(eval("6 & 3").unwrap(), 2.0);
+        assert_eq!(eval("6 | 1").unwrap(), 7.0);
+        assert_eq!(eval("6 ~ 3").unwrap(), 5.0);
+        assert_eq!(eval("~0").unwrap(), -1.0);
+    }
+
+    #[test]
+    fn shift_operators() {
+        assert_eq!(eval("1 << 4").unwrap(), 16.0);
+        assert_eq!(eval("256 >> 4").unwrap(), 16.0);
+    }
+
+    #[test]
+    fn shift_amount_out_of_range_is_domain_error() {
+        assert!(matches!(
+            eval("1 << 64"),
+            Err(CalcError::Math(MathError::DomainError(_)))
+        ));
+        assert!(matches!(
+            eval("1 << -1"),
+            Err(CalcError::Math(MathError::DomainError(_)))
+        ));
+    }
+
+    #[test]
+    fn dice_roll_sums_in_range() {
+        let mut vars: HashMap<String, f64> = HashMap::new();
+        let mut rng = Rng::new(42);
+        let result = evaluate("3d6", true, &mut vars, &mut rng).unwrap();
+        assert!((3.0..=18.0).contains(&result));
+    }
+
+    #[test]
+    fn dice_does_not_split_longer_identifier() {
+        // "density" must tokenize as a single Ident, not Number('d')+Ident("ensity").
+        let tokens = tokenize("10 density").unwrap();
+        assert!(matches!(tokens[1], Token::Ident(ref name) if name == "density"));
+    }
+
+    #[test]
+    fn dice_count_and_sides_are_capped() {
+        assert!(matches!(
+            eval("100000000000d6"),
+            Err(CalcError::Math(MathError::DomainError(_)))
+        ));
+        assert!(matches!(
+            eval("1d100000000000"),
+            Err(CalcError::Math(MathError::DomainError(_)))
+        ));
+    }
+
+    #[test]
+    fn syntax_error_pos_points_at_offending_char() {
+        match eval("1 + @") {
+            Err(CalcError::Syntax { pos, .. }) => assert_eq!(pos, 4),
+            other => panic!("expected Syntax error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn syntax_error_pos_is_shifted_across_assignment() {
+        // The '@' is at offset 8 in the full line, even though eval_expr only
+        // sees the RHS ("1 + @") starting at offset 4.
+        match eval("x = 1 + @") {
+            Err(CalcError::Syntax { pos, .. }) => assert_eq!(pos, 8),
+            other => panic!("expected Syntax error, got {:?}", other),
         }
     }
 }